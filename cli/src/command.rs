@@ -21,10 +21,18 @@ impl Command {
                 format!("Registering contract {}", contract.contract_id())
             }
             Command::Contracts => s!("Listring contracts"),
-            Command::State { contract_id } => format!("Quering state of {}", contract_id),
+            Command::State { contract_id, .. } => format!("Quering state of {}", contract_id),
             Command::Contract { contract_id } => {
                 format!("Retrieving contract source for {}", contract_id)
             }
+            Command::Forget { consignment } => {
+                format!("Forgetting consignment {}", consignment.id())
+            }
+            Command::Prune => s!("Pruning spent history from the stash"),
+            Command::Refresh => s!("Refreshing tentative witness statuses"),
+            Command::Disclose { disclosure } => {
+                format!("Processing disclosure {}", disclosure.id())
+            }
         }
     }
 }
@@ -43,14 +51,30 @@ impl Exec for Opts {
             Command::Contracts => {
                 client.list_contracts()?.iter().for_each(|id| println!("{}", id));
             }
-            Command::State { contract_id } => {
-                let state = client.contract_state(contract_id)?;
+            Command::State { contract_id, min_confirmations } => {
+                let state = client.owned_state(contract_id, min_confirmations)?;
                 println!("{}", serde_yaml::to_string(&state).unwrap());
             }
             Command::Contract { contract_id } => {
                 let contract = client.contract(contract_id)?;
                 println!("{}", contract);
             }
+            Command::Forget { consignment } => {
+                client.request(RpcMsg::Forget(consignment))?;
+                client.report_progress()?;
+            }
+            Command::Prune => {
+                client.request(RpcMsg::Prune)?;
+                client.report_progress()?;
+            }
+            Command::Refresh => {
+                client.request(RpcMsg::RefreshWitnesses)?;
+                client.report_progress()?;
+            }
+            Command::Disclose { disclosure } => {
+                client.request(RpcMsg::AddDisclosure(disclosure))?;
+                client.report_progress()?;
+            }
         };
 
         Ok(())
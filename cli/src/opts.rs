@@ -0,0 +1,72 @@
+// RGB node providing smart contracts functionality for Bitcoin & Lightning.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use clap::{Parser, Subcommand};
+use rgb::{Contract, ContractId, Disclosure};
+
+/// Command-line tool for working with an RGB node.
+#[derive(Parser, Clone, Debug)]
+#[clap(name = "rgb-cli", bin_name = "rgb-cli", author, version)]
+pub struct Opts {
+    /// Command to execute.
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+/// Command-line commands understood by the RGB node.
+#[derive(Subcommand, Clone, Debug)]
+pub enum Command {
+    /// Registers a contract with the node from a consignment.
+    Register {
+        /// Consignment carrying the contract genesis and its history.
+        contract: Contract,
+    },
+
+    /// Lists all known contracts.
+    Contracts,
+
+    /// Queries the state of a contract.
+    State {
+        /// Contract to query.
+        contract_id: ContractId,
+
+        /// Exclude transitions whose witness is unmined or confirmed below this
+        /// number of confirmations.
+        #[clap(short, long)]
+        min_confirmations: Option<u32>,
+    },
+
+    /// Retrieves the source of a contract.
+    Contract {
+        /// Contract to retrieve.
+        contract_id: ContractId,
+    },
+
+    /// Forgets a consignment, reclaiming storage from spent history no longer
+    /// needed to validate any remaining state tip.
+    Forget {
+        /// Consignment whose reachable-only-through-it nodes should be purged.
+        consignment: Contract,
+    },
+
+    /// Prunes spent history from the whole stash.
+    Prune,
+
+    /// Refreshes the mining status of tentative witnesses from electrum, so
+    /// that confirmation-filtered state queries count their transitions once
+    /// they mine.
+    Refresh,
+
+    /// Merges a disclosure of partial reveals into the stash.
+    Disclose {
+        /// Disclosure carrying anchors and revealed transitions/extensions.
+        disclosure: Disclosure,
+    },
+}
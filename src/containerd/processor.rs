@@ -9,14 +9,19 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
 
 use bitcoin::{OutPoint, Txid};
+use serde::{Deserialize, Serialize};
 use commit_verify::lnpbp4;
-use rgb::schema::TransitionType;
+use electrum_client::{ElectrumApi, Param};
+use serde_json::Value;
+use strict_encoding::{StrictDecode, StrictEncode};
+use rgb::schema::{OwnedRightType, TransitionType};
 use rgb::{
-    bundle, validation, Anchor, BundleId, ConsignmentType, ContractId, ContractState, Genesis,
-    InmemConsignment, Node, NodeId, NodeOutpoint, Schema, SchemaId, SealEndpoint, Transition,
-    TransitionBundle, Validator, Validity,
+    bundle, validation, Anchor, BundleId, ConsignmentType, ContractId, ContractState, Disclosure,
+    Extension, Genesis, InmemConsignment, Node, NodeId, NodeOutpoint, Schema, SchemaId,
+    SealEndpoint, Transition, TransitionBundle, Validator, Validity,
 };
 use rgb_rpc::OutpointSelection;
 
@@ -41,6 +46,9 @@ pub enum StashError {
     /// stash data storage.
     TransitionAbsent(NodeId),
 
+    /// node {0} violates the contract schema and can't be revealed.
+    SchemaViolation(NodeId),
+
     /// witness Txid is not known for transition {0}
     ///
     /// It may happen due to RGB Node bug, or indicate internal stash inconsistency and compromised
@@ -81,6 +89,123 @@ pub enum StashError {
     OutsizedBundle,
 }
 
+/// Mining status of a witness transaction, as tracked in
+/// [`Db::WITNESS_STATUS`] and refreshed from electrum.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+pub enum WitnessStatus {
+    /// the witness transaction is not known to the backing electrum server.
+    #[display("unknown")]
+    Unknown,
+
+    /// the witness transaction is seen in the mempool but not yet mined.
+    #[display("mempool")]
+    Mempool,
+
+    /// the witness transaction is mined at the given height with the given
+    /// number of confirmations.
+    #[display("mined at {height} ({confirmations} confirmations)")]
+    Mined {
+        /// block height at which the witness was mined.
+        height: u32,
+        /// number of confirmations on top of the witness, inclusive.
+        confirmations: u32,
+    },
+}
+
+impl WitnessStatus {
+    /// Number of confirmations of the witness, `0` while unmined.
+    pub fn confirmations(self) -> u32 {
+        match self {
+            WitnessStatus::Unknown | WitnessStatus::Mempool => 0,
+            WitnessStatus::Mined { confirmations, .. } => confirmations,
+        }
+    }
+
+    /// Whether the witness is mined at all, regardless of depth.
+    pub fn is_mined(self) -> bool { matches!(self, WitnessStatus::Mined { .. }) }
+
+    /// Whether the witness satisfies a `min_confirmations` threshold. A
+    /// threshold of `0` accepts any status, including still-tentative ones.
+    pub fn satisfies(self, min_confirmations: u32) -> bool {
+        min_confirmations == 0 || self.confirmations() >= min_confirmations
+    }
+
+    /// Whether the witness is buried deep enough to stop re-querying electrum.
+    pub fn is_final(self) -> bool { self.confirmations() >= 6 }
+}
+
+/// Spendable owned state of a contract, aggregated over live state tips only.
+///
+/// Aggregation is fungibility-aware: fungible assignment types are summed into
+/// a single spendable balance, while non-fungible and data types are listed as
+/// individual still-unspent allocations. Historical, already-spent outputs are
+/// excluded, so balances are not double-counted.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct OwnedState {
+    /// summed spendable balance per fungible assignment type.
+    pub balances: BTreeMap<OwnedRightType, u64>,
+    /// still-unspent allocations per non-fungible / data assignment type.
+    pub allocations: BTreeMap<OwnedRightType, Vec<NodeOutpoint>>,
+}
+
+impl Display for OwnedState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "confirmed balance:")?;
+        for (ty, amount) in &self.balances {
+            writeln!(f, "  type {}: {}", ty, amount)?;
+        }
+        writeln!(f, "owned allocations:")?;
+        for (ty, outpoints) in &self.allocations {
+            for outpoint in outpoints {
+                writeln!(f, "  type {}: {}", ty, outpoint)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Maps a flat owned-output number to the `(type index, index within type)`
+/// pair, given the assignment count of each owned-right type in iteration
+/// order. This is the single numbering convention used throughout the stash:
+/// a transition's owned outputs are numbered `0..n` across all of its
+/// owned-right types in order, and `parent_outputs()`/[`Db::CONTRACT_TIPS`]
+/// address them the same way. Returns `None` when the number is out of range.
+fn locate_output(
+    counts: impl IntoIterator<Item = u16>,
+    output_no: u16,
+) -> Option<(usize, u16)> {
+    let mut base = 0u16;
+    for (type_index, len) in counts.into_iter().enumerate() {
+        if output_no < base + len {
+            return Some((type_index, output_no - base));
+        }
+        base += len;
+    }
+    None
+}
+
+/// Maps a slot-local index to its position among only the revealed slots,
+/// given which of the assignment type's slots (in order) are revealed.
+/// Returns `None` when the slot itself is concealed.
+///
+/// Some revealed-state accessors are slot-aligned (one entry per assignment,
+/// concealed or not) while others are compacted to revealed entries only; see
+/// the commit that introduced this helper for the bug that motivated it. This
+/// lets a caller pair a trusted slot-aligned accessor with a second one whose
+/// alignment isn't guaranteed, without assuming the second one's shape.
+fn compacted_index(revealed: impl IntoIterator<Item = bool>, local: u16) -> Option<usize> {
+    let mut count = 0usize;
+    for (index, is_revealed) in revealed.into_iter().enumerate() {
+        if index as u16 == local {
+            return if is_revealed { Some(count) } else { None };
+        }
+        if is_revealed {
+            count += 1;
+        }
+    }
+    None
+}
+
 impl Runtime {
     pub(super) fn process_consignment<C: ConsignmentType>(
         &mut self,
@@ -98,6 +223,9 @@ impl Runtime {
         });
         trace!("Starting with contract state {:?}", state);
 
+        let mut tips: BTreeSet<NodeOutpoint> =
+            self.db.retrieve(Db::CONTRACT_TIPS, contract_id)?.unwrap_or_default();
+
         debug!("Validating consignment {} for contract {}", id, contract_id);
         let status = Validator::validate(&consignment, &self.electrum);
         info!("Consignment validation result is {}", status.validity());
@@ -136,6 +264,10 @@ impl Runtime {
             debug!("Restored anchor id is {}", anchor.anchor_id());
             trace!("Restored anchor: {:?}", anchor);
             self.db.store_merge_h(Db::ANCHORS, anchor.txid, anchor)?;
+
+            let witness_status = self.resolve_witness_status(witness_txid)?;
+            debug!("Witness {} status is {}", witness_txid, witness_status);
+            self.db.store_h(Db::WITNESS_STATUS, witness_txid, &witness_status)?;
             let mut data =
                 bundle.concealed_iter().map(|(id, set)| (*id, set.clone())).collect::<Vec<_>>();
             for (transition, inputs) in bundle.into_revealed_iter() {
@@ -144,10 +276,25 @@ impl Runtime {
                 debug!("Processing state transition {}", node_id);
                 trace!("State transition: {:?}", transition);
 
-                // TODO: For owned state, use only state which is a part of state tips
                 state.add_transition(witness_txid, &transition);
                 trace!("Contract state now is {:?}", state);
 
+                // Maintain the set of unspent state tips: every parent output
+                // consumed by this transition stops being a tip, and every
+                // owned-right assignment of this transition becomes one. Own
+                // outputs are numbered with the same flat per-assignment index
+                // that `parent_outputs()` yields, so that removal matches.
+                for parent in transition.parent_outputs() {
+                    tips.remove(&parent);
+                }
+                let mut output_no = 0u16;
+                for (_, assignments) in transition.owned_rights().iter() {
+                    for _ in 0..assignments.len() {
+                        tips.insert(NodeOutpoint::new(node_id, output_no));
+                        output_no += 1;
+                    }
+                }
+
                 trace!("Storing state transition data");
                 data.push((node_id, inputs.clone()));
                 self.db.store_merge(Db::TRANSITIONS, node_id, transition)?;
@@ -164,7 +311,6 @@ impl Runtime {
             debug!("Processing state extension {}", node_id);
             trace!("State transition: {:?}", extension);
 
-            // TODO: For owned state, use only state which is a part of state tips
             state.add_extension(&extension);
             trace!("Contract state now is {:?}", state);
 
@@ -174,11 +320,595 @@ impl Runtime {
         debug!("Storing contract state for {}", contract_id);
         trace!("Final contract state is {:?}", state);
         self.db.store(Db::CONTRACTS, contract_id, &state)?;
+        trace!("Final state tips are {:?}", tips);
+        self.db.store(Db::CONTRACT_TIPS, contract_id, &tips)?;
 
         info!("Consignment processing complete for {}", id);
         Ok(status)
     }
 
+    /// Queries the backing electrum server for the current mining status of a
+    /// witness transaction.
+    fn resolve_witness_status(&self, txid: Txid) -> Result<WitnessStatus, DaemonError> {
+        // `blockchain.transaction.get` with the verbose flag reports the
+        // confirmation count directly, so we don't need the containing height
+        // up front (which `transaction_get_merkle` would otherwise require).
+        let verbose = match self
+            .electrum
+            .raw_call("blockchain.transaction.get", vec![
+                Param::String(txid.to_string()),
+                Param::Bool(true),
+            ]) {
+            Ok(verbose) => verbose,
+            Err(_) => return Ok(WitnessStatus::Unknown),
+        };
+
+        match verbose.get("confirmations").and_then(Value::as_u64) {
+            Some(confirmations) if confirmations > 0 => {
+                let tip = self.electrum.block_headers_subscribe()?.height as u32;
+                let confirmations = confirmations as u32;
+                let height = tip.saturating_sub(confirmations.saturating_sub(1));
+                Ok(WitnessStatus::Mined { height, confirmations })
+            }
+            _ => Ok(WitnessStatus::Mempool),
+        }
+    }
+
+    /// Rebuilds the state of `contract_id`, optionally excluding transitions
+    /// whose witness transaction is unmined or confirmed below
+    /// `min_confirmations`. A `None` (or zero) threshold yields the full state
+    /// including still-tentative, forced imports.
+    pub(super) fn contract_state(
+        &mut self,
+        contract_id: ContractId,
+        min_confirmations: Option<u32>,
+    ) -> Result<ContractState, DaemonError> {
+        let min = min_confirmations.unwrap_or_default();
+        let genesis: Genesis =
+            self.db.retrieve(Db::GENESIS, contract_id)?.ok_or(StashError::GenesisAbsent)?;
+        if min == 0 {
+            return self
+                .db
+                .retrieve(Db::CONTRACTS, contract_id)?
+                .map(Ok)
+                .unwrap_or_else(|| Ok(ContractState::with(contract_id, &genesis)));
+        }
+
+        let mut state = ContractState::with(contract_id, &genesis);
+        for node_id in self.contract_transitions(contract_id)? {
+            let witness_txid: Txid = self
+                .db
+                .retrieve(Db::TRANSITION_TXID, node_id)?
+                .ok_or(StashError::TransitionTxidAbsent(node_id))?;
+            let status = self
+                .db
+                .retrieve_h::<_, WitnessStatus>(Db::WITNESS_STATUS, witness_txid)?
+                .unwrap_or(WitnessStatus::Unknown);
+            if !status.satisfies(min) {
+                continue;
+            }
+            let transition: Transition = self
+                .db
+                .retrieve(Db::TRANSITIONS, node_id)?
+                .ok_or(StashError::TransitionAbsent(node_id))?;
+            state.add_transition(witness_txid, &transition);
+        }
+        for node_id in self.db.extensions_of(contract_id)? {
+            if let Some(extension) = self.db.retrieve(Db::EXTENSIONS, node_id)? {
+                state.add_extension(&extension);
+            }
+        }
+        Ok(state)
+    }
+
+    /// Aggregates the spendable owned state of `contract_id` over its live
+    /// state tips, optionally excluding transitions whose witness is unmined or
+    /// confirmed below `min_confirmations`. Fungible assignment types are
+    /// summed into a spendable balance; non-fungible and data types are listed
+    /// as still-unspent allocations.
+    pub(super) fn owned_state(
+        &mut self,
+        contract_id: ContractId,
+        min_confirmations: Option<u32>,
+    ) -> Result<OwnedState, DaemonError> {
+        let min = min_confirmations.unwrap_or_default();
+        let tips: BTreeSet<NodeOutpoint> =
+            self.db.retrieve(Db::CONTRACT_TIPS, contract_id)?.unwrap_or_default();
+
+        let mut owned = OwnedState::default();
+        for tip in tips {
+            let transition: Transition = self
+                .db
+                .retrieve(Db::TRANSITIONS, tip.node_id)?
+                .ok_or(StashError::TransitionAbsent(tip.node_id))?;
+
+            if min > 0 {
+                let witness_txid: Txid = self
+                    .db
+                    .retrieve(Db::TRANSITION_TXID, tip.node_id)?
+                    .ok_or(StashError::TransitionTxidAbsent(tip.node_id))?;
+                let status = self
+                    .db
+                    .retrieve_h::<_, WitnessStatus>(Db::WITNESS_STATUS, witness_txid)?
+                    .unwrap_or(WitnessStatus::Unknown);
+                if !status.satisfies(min) {
+                    continue;
+                }
+            }
+
+            // Locate the single assignment addressed by the tip's flat output
+            // number, rather than the whole type's assignment group.
+            let counts = transition.owned_rights().iter().map(|(_, a)| a.len() as u16);
+            let Some((type_index, local)) = locate_output(counts, tip.output_no) else {
+                continue;
+            };
+            let (ty, assignments) = transition
+                .owned_rights()
+                .iter()
+                .nth(type_index)
+                .expect("locate_output returned an in-range type index");
+            // Fungibility is read off the assignment representation itself,
+            // mirroring rgb-std's `KnownState::IS_FUNGIBLE`.
+            if assignments.is_value() {
+                // `as_revealed_state_amounts()` is not known to be slot-aligned
+                // the way `as_revealed_state_seals()` is (`Collector::process`
+                // relies on the latter for its per-slot output numbering), so
+                // derive the amount's position from the trusted seal alignment
+                // instead of indexing by `local` directly.
+                let revealed = assignments.as_revealed_state_seals().iter().map(Option::is_some);
+                let amount = compacted_index(revealed, local)
+                    .and_then(|index| assignments.as_revealed_state_amounts().get(index))
+                    .copied()
+                    .unwrap_or(0);
+                *owned.balances.entry(*ty).or_default() += amount;
+            } else {
+                owned.allocations.entry(*ty).or_default().push(tip);
+            }
+        }
+        Ok(owned)
+    }
+
+    /// Background refresh pass: re-queries electrum for every witness that is
+    /// not yet final and records any newly gained confirmations in
+    /// [`Db::WITNESS_STATUS`], which the `min_confirmations` views of
+    /// `contract_state` and `owned_state` read, so a forced (non-mined) import
+    /// is counted by them once its witness confirms. The [`Db::CONTRACTS`]
+    /// entry of a contract with a newly mined witness is stored again without
+    /// change; no transition is marked as confirmed there. Returns the number
+    /// of witnesses newly promoted to a mined status.
+    pub(super) fn refresh_witnesses(&mut self) -> Result<usize, DaemonError> {
+        let mut promoted = 0usize;
+        for contract_id in self.db.contract_ids()? {
+            let mut dirty = false;
+            let mut witness_txids: BTreeSet<Txid> = bset![];
+            for node_id in self.contract_transitions(contract_id)? {
+                if let Some(txid) = self.db.retrieve::<_, Txid>(Db::TRANSITION_TXID, node_id)? {
+                    witness_txids.insert(txid);
+                }
+            }
+            for witness_txid in witness_txids {
+                let current = self
+                    .db
+                    .retrieve_h::<_, WitnessStatus>(Db::WITNESS_STATUS, witness_txid)?
+                    .unwrap_or(WitnessStatus::Unknown);
+                if current.is_final() {
+                    continue;
+                }
+                let refreshed = self.resolve_witness_status(witness_txid)?;
+                if refreshed == current {
+                    continue;
+                }
+                if refreshed.confirmations() < current.confirmations() {
+                    // A transient electrum hiccup resolves to `Unknown`; never let
+                    // it demote an already-mined witness back down.
+                    warn!(
+                        "Ignoring downgrade of witness {} from {} to {}",
+                        witness_txid, current, refreshed
+                    );
+                    continue;
+                }
+                debug!("Witness {} promoted from {} to {}", witness_txid, current, refreshed);
+                self.db.store_h(Db::WITNESS_STATUS, witness_txid, &refreshed)?;
+                if refreshed.is_mined() && !current.is_mined() {
+                    promoted += 1;
+                    dirty = true;
+                }
+            }
+            if dirty {
+                let state = self.contract_state(contract_id, None)?;
+                self.db.store(Db::CONTRACTS, contract_id, &state)?;
+            }
+        }
+        Ok(promoted)
+    }
+
+    /// Merges a [`Disclosure`] of partial reveals (e.g. blinded seal openings
+    /// learned out-of-band) into the stash, checking revealed nodes against the
+    /// already-stored genesis and schema for their [`ContractId`]. All revealed
+    /// extensions of a contract are checked before any of them is written, and
+    /// all revealed transitions of a bundle before anything of that bundle is
+    /// written. Extensions and bundles are processed in turn, so a violation
+    /// found in a later bundle returns an error without undoing what was
+    /// already written for the extensions and the earlier bundles.
+    ///
+    /// Mirrors the `know_about` method of the upstream `Stash` trait. A
+    /// disclosure carries anchors and revealed transitions/extensions but no
+    /// genesis, so a reveal referencing a contract that has never been imported
+    /// is rejected with [`StashError::GenesisAbsent`].
+    pub(super) fn process_disclosure(
+        &mut self,
+        disclosure: Disclosure,
+    ) -> Result<(), DaemonError> {
+        let id = disclosure.id();
+        info!("Processing disclosure {}", id);
+
+        for (contract_id, extensions) in disclosure.extensions() {
+            let genesis: Genesis = self
+                .db
+                .retrieve(Db::GENESIS, *contract_id)?
+                .ok_or(StashError::GenesisAbsent)?;
+            let schema_id = genesis.schema_id();
+            let schema: Schema = self
+                .db
+                .retrieve(Db::SCHEMATA, schema_id)?
+                .ok_or(StashError::SchemaAbsent(schema_id))?;
+
+            // Validate every revealed extension against the schema before
+            // writing any of them for this contract, mirroring the
+            // all-or-nothing validation `process_consignment` does up front.
+            for extension in extensions {
+                let extension_type = extension.extension_type();
+                if !schema.extensions.contains_key(&extension_type) {
+                    return Err(StashError::SchemaViolation(extension.node_id()).into());
+                }
+            }
+
+            let mut state = self
+                .db
+                .retrieve(Db::CONTRACTS, *contract_id)?
+                .unwrap_or_else(|| ContractState::with(*contract_id, &genesis));
+            for extension in extensions {
+                let node_id = extension.node_id();
+                debug!("Revealing state extension {}", node_id);
+                state.add_extension(extension);
+                self.db.store_merge(Db::EXTENSIONS, node_id, extension.clone())?;
+            }
+            self.db.store(Db::CONTRACTS, *contract_id, &state)?;
+        }
+
+        for (anchor, bundles) in disclosure.into_anchored_bundles() {
+            let witness_txid = anchor.txid;
+            let anchor = anchor.into_merkle_block_map();
+            for (contract_id, bundle) in bundles {
+                debug!("Revealing bundle for contract {} at txid {}", contract_id, witness_txid);
+
+                let genesis: Genesis = self
+                    .db
+                    .retrieve(Db::GENESIS, contract_id)?
+                    .ok_or(StashError::GenesisAbsent)?;
+                let schema_id = genesis.schema_id();
+                let schema: Schema = self
+                    .db
+                    .retrieve(Db::SCHEMATA, schema_id)?
+                    .ok_or(StashError::SchemaAbsent(schema_id))?;
+
+                // Validate every revealed transition against the schema
+                // before writing anything for this witness/contract: otherwise
+                // a bad transition later in the bundle would leave the anchor
+                // and earlier transitions committed instead of failing
+                // atomically, the way `process_consignment` validates up front.
+                for (transition, _) in bundle.revealed_iter() {
+                    let transition_type = transition.transition_type();
+                    if !schema.transitions.contains_key(&transition_type) {
+                        return Err(StashError::SchemaViolation(transition.node_id()).into());
+                    }
+                }
+
+                if let Some(anchor) = anchor.get(&contract_id) {
+                    self.db.store_merge_h(Db::ANCHORS, witness_txid, anchor.clone())?;
+                }
+
+                let mut state = self
+                    .db
+                    .retrieve(Db::CONTRACTS, contract_id)?
+                    .unwrap_or_else(|| ContractState::with(contract_id, &genesis));
+
+                // Merge the reveal into the already-stored bundle when one
+                // exists; otherwise the disclosed bundle is the first we see.
+                let existing: Option<TransitionBundle> =
+                    self.db.retrieve_h(Db::BUNDLES, witness_txid)?;
+                let had_existing = existing.is_some();
+                let mut merged = existing.unwrap_or_else(|| bundle.clone());
+
+                // Maintain the set of unspent state tips the same way
+                // `process_consignment` does: a disclosed transition is just as
+                // live as a consigned one, so skipping this left disclosed
+                // transitions invisible to `owned_state` and, worse, made
+                // `prune` treat them as unreachable garbage.
+                let mut tips: BTreeSet<NodeOutpoint> =
+                    self.db.retrieve(Db::CONTRACT_TIPS, contract_id)?.unwrap_or_default();
+
+                for (transition, _) in bundle.into_revealed_iter() {
+                    let node_id = transition.node_id();
+                    let transition_type = transition.transition_type();
+                    debug!("Revealing state transition {}", node_id);
+
+                    state.add_transition(witness_txid, &transition);
+                    if had_existing {
+                        merged.reveal_transition(transition.clone())?;
+                    }
+
+                    for parent in transition.parent_outputs() {
+                        tips.remove(&parent);
+                    }
+                    let mut output_no = 0u16;
+                    for (_, assignments) in transition.owned_rights().iter() {
+                        for _ in 0..assignments.len() {
+                            tips.insert(NodeOutpoint::new(node_id, output_no));
+                            output_no += 1;
+                        }
+                    }
+
+                    self.db.store_merge(Db::TRANSITIONS, node_id, transition)?;
+                    self.db.store(Db::TRANSITION_TXID, node_id, &witness_txid)?;
+
+                    let index_id = Db::index_two_pieces(contract_id, transition_type);
+                    self.db.insert_into_set(Db::CONTRACT_TRANSITIONS, index_id, node_id)?;
+                }
+
+                // Persist the merged bundle itself so every node appears once.
+                let mut data = merged
+                    .concealed_iter()
+                    .map(|(id, set)| (*id, set.clone()))
+                    .collect::<Vec<_>>();
+                data.extend(
+                    merged
+                        .revealed_iter()
+                        .map(|(transition, inputs)| (transition.node_id(), inputs.clone())),
+                );
+                self.db.store_h(Db::BUNDLES, witness_txid, &data)?;
+                self.db.store(Db::CONTRACTS, contract_id, &state)?;
+                self.db.store(Db::CONTRACT_TIPS, contract_id, &tips)?;
+            }
+        }
+
+        info!("Disclosure processing complete for {}", id);
+        Ok(())
+    }
+
+    /// Removes from the stash every node reachable only through `consignment`
+    /// which is no longer required to validate any remaining state tip,
+    /// returning the number of purged stash entries.
+    ///
+    /// Mirrors the `forget` method of the upstream `Stash` trait. The critical
+    /// invariant is that a node which is still an ancestor of a live tip of any
+    /// other branch is never removed; this is enforced by reference-counting the
+    /// live ancestry via [`Runtime::live_ancestry`] before any deletion.
+    pub(super) fn forget_contract<C: ConsignmentType>(
+        &mut self,
+        consignment: InmemConsignment<C>,
+    ) -> Result<usize, DaemonError> {
+        let contract_id = consignment.contract_id();
+        let id = consignment.id();
+
+        info!("Forgetting consignment {} for contract {}", id, contract_id);
+
+        // Collect the node ids carried by the consignment being forgotten,
+        // transitions and extensions separately, since they are stored (and
+        // kept-alive) through distinct paths.
+        let mut forgotten_transitions: BTreeSet<NodeId> = bset![];
+        for (_, bundle) in &consignment.anchored_bundles {
+            for (node_id, _) in bundle.revealed_iter() {
+                forgotten_transitions.insert(node_id.node_id());
+            }
+        }
+        let mut forgotten_extensions: BTreeSet<NodeId> = bset![];
+        for extension in &consignment.state_extensions {
+            forgotten_extensions.insert(extension.node_id());
+        }
+
+        // Everything still reachable from a live tip must be kept.
+        let (keep_transitions, keep_extensions) = self.live_ancestry(contract_id)?;
+        let forgettable_transitions =
+            forgotten_transitions.difference(&keep_transitions).copied().collect();
+        let forgettable_extensions =
+            forgotten_extensions.difference(&keep_extensions).copied().collect();
+
+        self.prune_contract(contract_id, &forgettable_transitions, &forgettable_extensions)
+    }
+
+    /// Walks every known contract, recomputes the set of transitions and
+    /// extensions still connected to a current unspent seal tip, and deletes
+    /// transitions, extensions, anchors and bundles no longer referenced by
+    /// any tip, garbage-collecting the [`Db::CONTRACT_TRANSITIONS`] index and
+    /// orphaned [`Db::TRANSITION_TXID`] entries. Returns the number of purged
+    /// entries.
+    ///
+    /// Mirrors the `prune` method of the upstream `Stash` trait.
+    pub(super) fn prune(&mut self) -> Result<usize, DaemonError> {
+        info!("Pruning spent history from the whole stash");
+        let mut purged = 0usize;
+        for contract_id in self.db.contract_ids()? {
+            let all_transitions = self.contract_transitions(contract_id)?;
+            let all_extensions = self.contract_extensions(contract_id)?;
+            let (keep_transitions, keep_extensions) = self.live_ancestry(contract_id)?;
+            let forgettable_transitions =
+                all_transitions.difference(&keep_transitions).copied().collect();
+            let forgettable_extensions =
+                all_extensions.difference(&keep_extensions).copied().collect();
+            purged +=
+                self.prune_contract(contract_id, &forgettable_transitions, &forgettable_extensions)?;
+        }
+        info!("Pruned {} stash entries", purged);
+        Ok(purged)
+    }
+
+    /// Returns the transition and extension node ids of `contract_id` that are
+    /// still reachable from its live state tips in [`Db::CONTRACT_TIPS`],
+    /// walking [`Node::parent_outputs`] (into further transitions) and
+    /// [`Node::parent_public_rights`] (into the extensions they reveal state
+    /// through) the same way [`Collector::iterate`] walks a consignment's
+    /// endpoints back to genesis. Nodes outside of this set carry only spent
+    /// state and are safe to forget.
+    fn live_ancestry(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<(BTreeSet<NodeId>, BTreeSet<NodeId>), DaemonError> {
+        let tips: BTreeSet<NodeOutpoint> =
+            self.db.retrieve(Db::CONTRACT_TIPS, contract_id)?.unwrap_or_default();
+
+        let mut keep_transitions: BTreeSet<NodeId> = bset![];
+        let mut keep_extensions: BTreeSet<NodeId> = bset![];
+        let mut transition_frontier: Vec<NodeId> = tips.into_iter().map(|tip| tip.node_id).collect();
+        let mut extension_frontier: Vec<NodeId> = vec![];
+
+        loop {
+            while let Some(node_id) = transition_frontier.pop() {
+                if !keep_transitions.insert(node_id) {
+                    continue;
+                }
+                if let Some(transition) =
+                    self.db.retrieve::<_, Transition>(Db::TRANSITIONS, node_id)?
+                {
+                    transition_frontier
+                        .extend(transition.parent_outputs().into_iter().map(|out| out.node_id));
+                    extension_frontier
+                        .extend(transition.parent_public_rights().iter().map(|(id, _)| *id));
+                }
+            }
+            while let Some(node_id) = extension_frontier.pop() {
+                if !keep_extensions.insert(node_id) {
+                    continue;
+                }
+                if let Some(extension) = self.db.retrieve::<_, Extension>(Db::EXTENSIONS, node_id)? {
+                    transition_frontier
+                        .extend(extension.parent_outputs().into_iter().map(|out| out.node_id));
+                    extension_frontier
+                        .extend(extension.parent_public_rights().iter().map(|(id, _)| *id));
+                }
+            }
+            if transition_frontier.is_empty() && extension_frontier.is_empty() {
+                break;
+            }
+        }
+
+        Ok((keep_transitions, keep_extensions))
+    }
+
+    /// Enumerates every transition node id stored for `contract_id` across all
+    /// of its schema transition types.
+    fn contract_transitions(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<BTreeSet<NodeId>, DaemonError> {
+        let genesis: Genesis =
+            self.db.retrieve(Db::GENESIS, contract_id)?.ok_or(StashError::GenesisAbsent)?;
+        let schema_id = genesis.schema_id();
+        let schema: Schema = self
+            .db
+            .retrieve(Db::SCHEMATA, schema_id)?
+            .ok_or(StashError::SchemaAbsent(schema_id))?;
+
+        let mut node_ids: BTreeSet<NodeId> = bset![];
+        for transition_type in schema.transitions.keys() {
+            node_ids.extend(self.db.transitions_by_type(contract_id, *transition_type)?);
+        }
+        Ok(node_ids)
+    }
+
+    /// Enumerates every state extension node id stored for `contract_id`.
+    fn contract_extensions(&mut self, contract_id: ContractId) -> Result<BTreeSet<NodeId>, DaemonError> {
+        Ok(self.db.extensions_of(contract_id)?.into_iter().collect())
+    }
+
+    /// Deletes the given `forgettable_transitions` and `forgettable_extensions`
+    /// of `contract_id`, dropping their [`Db::CONTRACT_TRANSITIONS`] index
+    /// entries and [`Db::TRANSITION_TXID`] pointers, and garbage-collecting any
+    /// anchor and bundle whose witness txid is no longer referenced by a
+    /// surviving transition. Returns the number of purged stash entries.
+    fn prune_contract(
+        &mut self,
+        contract_id: ContractId,
+        forgettable_transitions: &BTreeSet<NodeId>,
+        forgettable_extensions: &BTreeSet<NodeId>,
+    ) -> Result<usize, DaemonError> {
+        let mut purged = 0usize;
+        let mut touched_txids: BTreeSet<Txid> = bset![];
+        for node_id in forgettable_transitions {
+            let transition: Transition = self
+                .db
+                .retrieve(Db::TRANSITIONS, *node_id)?
+                .ok_or(StashError::TransitionAbsent(*node_id))?;
+            let transition_type = transition.transition_type();
+            debug!("Forgetting state transition {}", node_id);
+
+            if let Some(witness_txid) =
+                self.db.retrieve::<_, Txid>(Db::TRANSITION_TXID, *node_id)?
+            {
+                touched_txids.insert(witness_txid);
+                self.db.delete(Db::TRANSITION_TXID, *node_id)?;
+                purged += 1;
+            }
+
+            let index_id = Db::index_two_pieces(contract_id, transition_type);
+            self.db.remove_from_set(Db::CONTRACT_TRANSITIONS, index_id, *node_id)?;
+            self.db.delete(Db::TRANSITIONS, *node_id)?;
+            purged += 1;
+        }
+
+        for node_id in forgettable_extensions {
+            debug!("Forgetting state extension {}", node_id);
+            self.db.delete(Db::EXTENSIONS, *node_id)?;
+            purged += 1;
+        }
+
+        // Garbage-collect anchors and bundles whose witness is now orphaned.
+        let surviving = self.contract_transitions(contract_id)?;
+        let mut live_txids: BTreeSet<Txid> = bset![];
+        for node_id in &surviving {
+            if let Some(txid) = self.db.retrieve::<_, Txid>(Db::TRANSITION_TXID, *node_id)? {
+                live_txids.insert(txid);
+            }
+        }
+        let orphaned: Vec<Txid> = touched_txids.difference(&live_txids).copied().collect();
+        for witness_txid in orphaned {
+            // Anchors (and their bundles) are keyed by txid because a single
+            // witness can commit to several contracts, so only garbage-collect
+            // once no other contract still references it.
+            if self.witness_referenced_elsewhere(contract_id, witness_txid)? {
+                continue;
+            }
+            debug!("Garbage-collecting orphaned anchor and bundle for txid {}", witness_txid);
+            self.db.delete_h(Db::BUNDLES, witness_txid)?;
+            self.db.delete_h(Db::ANCHORS, witness_txid)?;
+            purged += 2;
+        }
+
+        Ok(purged)
+    }
+
+    /// Whether any contract other than `skip` still has a transition whose
+    /// witness is `witness_txid`, guarding shared anchors/bundles from deletion.
+    fn witness_referenced_elsewhere(
+        &mut self,
+        skip: ContractId,
+        witness_txid: Txid,
+    ) -> Result<bool, DaemonError> {
+        for contract_id in self.db.contract_ids()? {
+            if contract_id == skip {
+                continue;
+            }
+            for node_id in self.contract_transitions(contract_id)? {
+                if self.db.retrieve::<_, Txid>(Db::TRANSITION_TXID, node_id)?
+                    == Some(witness_txid)
+                {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
     pub(super) fn compose_consignment<T: ConsignmentType>(
         &mut self,
         contract_id: ContractId,
@@ -220,8 +950,11 @@ impl Runtime {
 struct Collector {
     pub contract_id: ContractId,
     pub anchored_bundles: BTreeMap<Txid, (Anchor<lnpbp4::MerkleProof>, TransitionBundle)>,
+    pub state_extensions: BTreeSet<Extension>,
     pub endpoints: Vec<(BundleId, SealEndpoint)>,
     pub endpoint_inputs: Vec<NodeId>,
+    pub extension_inputs: Vec<NodeId>,
+    pub visited: BTreeSet<NodeId>,
 }
 
 impl Collector {
@@ -229,12 +962,14 @@ impl Collector {
         Collector {
             contract_id,
             anchored_bundles: empty![],
+            state_extensions: empty![],
             endpoints: vec![],
             endpoint_inputs: vec![],
+            extension_inputs: vec![],
+            visited: empty![],
         }
     }
 
-    // TODO: Support state extensions
     pub fn process(
         &mut self,
         db: &mut Db,
@@ -245,6 +980,12 @@ impl Collector {
 
         let mut tips: Vec<NodeOutpoint> = vec![];
         for transition_id in node_ids {
+            // Guard against revealing the same transition twice when it is
+            // reachable both as an endpoint and through an extension's parents.
+            if !self.visited.insert(transition_id) {
+                continue;
+            }
+
             let transition: Transition = db
                 .retrieve(Db::TRANSITIONS, transition_id)?
                 .ok_or(StashError::TransitionAbsent(transition_id))?;
@@ -268,20 +1009,36 @@ impl Collector {
             };
 
             let bundle_id = bundle.bundle_id();
-            for (output_no, (_, assignments)) in transition.owned_rights().iter().enumerate() {
-                for seal in assignments.filter_revealed_seals() {
-                    let txid = seal.txid.unwrap_or(witness_txid);
-                    let outpoint = OutPoint::new(txid, seal.vout);
-                    let seal_endpoint = SealEndpoint::from(seal);
-                    if outpoint_selection.includes(outpoint) {
-                        tips.push(NodeOutpoint::new(transition_id, output_no as u16));
-                        self.endpoints.push((bundle_id, seal_endpoint));
-                        self.endpoint_inputs
-                            .extend(transition.parent_outputs().into_iter().map(|out| out.node_id));
+            // Number owned outputs with the same flat per-assignment scheme as
+            // `process_consignment` and `owned_state` (see `locate_output`):
+            // `output_no` advances once per assignment *slot*, revealed or
+            // concealed, so it stays aligned even when a type mixes the two.
+            // A concealed slot still occupies an output number, it simply
+            // isn't eligible to be selected as a tip here.
+            let mut output_no = 0u16;
+            for (_, assignments) in transition.owned_rights().iter() {
+                for seal in assignments.as_revealed_state_seals() {
+                    if let Some(seal) = seal {
+                        let txid = seal.txid.unwrap_or(witness_txid);
+                        let outpoint = OutPoint::new(txid, seal.vout);
+                        let seal_endpoint = SealEndpoint::from(seal);
+                        if outpoint_selection.includes(outpoint) {
+                            tips.push(NodeOutpoint::new(transition_id, output_no));
+                            self.endpoints.push((bundle_id, seal_endpoint));
+                            self.endpoint_inputs.extend(
+                                transition.parent_outputs().into_iter().map(|out| out.node_id),
+                            );
+                        }
                     }
+                    output_no += 1;
                 }
             }
 
+            // Any extension referenced through the transition's public-rights
+            // parents becomes a frontier node for extension-graph traversal.
+            self.extension_inputs
+                .extend(transition.parent_public_rights().iter().map(|(node_id, _)| *node_id));
+
             bundle.reveal_transition(transition)?;
         }
 
@@ -289,12 +1046,35 @@ impl Collector {
     }
 
     pub fn iterate(mut self, db: &mut Db) -> Result<Self, DaemonError> {
-        // Collect all transitions between endpoints and genesis independently from their type
+        // Collect all transitions and extensions between endpoints and genesis
+        // independently from their type, de-duplicating by node id and guarding
+        // against cycles via `visited`.
         loop {
             let node_ids = self.endpoint_inputs;
             self.endpoint_inputs = vec![];
             self.process(db, node_ids, &OutpointSelection::All)?;
-            if self.endpoint_inputs.is_empty() {
+
+            let extension_inputs = self.extension_inputs;
+            self.extension_inputs = vec![];
+            for node_id in extension_inputs {
+                if !self.visited.insert(node_id) {
+                    continue;
+                }
+                // A public-rights parent may point at the genesis rather than an
+                // extension; such node ids are simply absent from `EXTENSIONS`.
+                let Some(extension) = db.retrieve::<_, Extension>(Db::EXTENSIONS, node_id)? else {
+                    continue;
+                };
+                // Recurse through the extension's own parents, which may be
+                // further transitions or extensions.
+                self.endpoint_inputs
+                    .extend(extension.parent_outputs().into_iter().map(|out| out.node_id));
+                self.extension_inputs
+                    .extend(extension.parent_public_rights().iter().map(|(id, _)| *id));
+                self.state_extensions.insert(extension);
+            }
+
+            if self.endpoint_inputs.is_empty() && self.extension_inputs.is_empty() {
                 break;
             }
         }
@@ -316,6 +1096,7 @@ impl Collector {
             .map_err(|_| StashError::OutsizedBundle)?;
 
         let tips = tips.into_iter().collect();
+        let state_extensions = self.state_extensions.into_iter().collect();
 
         Ok(InmemConsignment::<T>::with(
             schema,
@@ -324,7 +1105,75 @@ impl Collector {
             tips,
             self.endpoints,
             anchored_bundles,
-            empty!(),
+            state_extensions,
         ))
     }
 }
+
+// Only the logic that is fully self-contained (no `Db`/`rgb` fixtures) is
+// covered here: the stash's invariants around tips, disclosure merging and
+// extension-graph traversal are exercised through live `Db`/`Validator`
+// state and have no in-crate mocks to drive them without one.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locate_output_finds_type_and_local_index() {
+        let counts = [2u16, 0, 3];
+        assert_eq!(locate_output(counts, 0), Some((0, 0)));
+        assert_eq!(locate_output(counts, 1), Some((0, 1)));
+        assert_eq!(locate_output(counts, 2), Some((2, 0)));
+        assert_eq!(locate_output(counts, 4), Some((2, 2)));
+    }
+
+    #[test]
+    fn locate_output_rejects_out_of_range() {
+        let counts = [2u16, 3];
+        assert_eq!(locate_output(counts, 5), None);
+    }
+
+    #[test]
+    fn compacted_index_skips_concealed_slots() {
+        // slots: revealed, concealed, revealed, revealed
+        let revealed = [true, false, true, true];
+        assert_eq!(compacted_index(revealed, 0), Some(0));
+        assert_eq!(compacted_index(revealed, 1), None);
+        assert_eq!(compacted_index(revealed, 2), Some(1));
+        assert_eq!(compacted_index(revealed, 3), Some(2));
+    }
+
+    #[test]
+    fn compacted_index_out_of_range_is_none() {
+        let revealed = [true, true];
+        assert_eq!(compacted_index(revealed, 2), None);
+    }
+
+    #[test]
+    fn witness_status_confirmations_and_finality() {
+        assert_eq!(WitnessStatus::Unknown.confirmations(), 0);
+        assert_eq!(WitnessStatus::Mempool.confirmations(), 0);
+        assert!(!WitnessStatus::Mempool.is_mined());
+
+        let shallow = WitnessStatus::Mined { height: 100, confirmations: 1 };
+        assert!(shallow.is_mined());
+        assert!(!shallow.is_final());
+        assert!(shallow.satisfies(1));
+        assert!(!shallow.satisfies(2));
+
+        let deep = WitnessStatus::Mined { height: 100, confirmations: 6 };
+        assert!(deep.is_final());
+        assert!(deep.satisfies(0));
+    }
+
+    #[test]
+    fn witness_status_unknown_ranks_below_mined() {
+        // The guard in `refresh_witnesses` skips a refreshed status with fewer
+        // confirmations than the stored one. This checks that `Unknown` (what
+        // a transient electrum failure resolves to) ranks below an
+        // already-mined witness, so that guard catches it.
+        let current = WitnessStatus::Mined { height: 100, confirmations: 3 };
+        let refreshed = WitnessStatus::Unknown;
+        assert!(refreshed.confirmations() < current.confirmations());
+    }
+}